@@ -1,13 +1,21 @@
 use betterjeb::*;
+use betterjeb::events;
+use betterjeb::maneuver;
+use betterjeb::telemetry;
+use betterjeb::throttle;
 use betterjeb::util::countdown;
 
-use krpc_mars::{batch_call, batch_call_common, StreamHandle};
+use krpc_mars::{batch_call, batch_call_common, StreamHandle, StreamUpdate};
 use std::error::Error;
 
 const TURN_START_ALT: f64 = 250.;
 const TURN_END_ALT: f64 = 45_000.;
 const TARGET_ALTITUDE: f64 = 74_000.;
+const TARGET_INCLINATION: f64 = 0.;
+const TARGET_TWR: f64 = 2.0;
+const MAX_DYNAMIC_PRESSURE: f64 = 24_000.;
 const SRB_FUEL: &str = "SolidFuel";
+const TELEMETRY_LOG: &str = "flight_telemetry.jsonl";
 
 fn main() -> Result<()> {
     env_logger::init();
@@ -48,14 +56,56 @@ fn main() -> Result<()> {
     let auto_pilot = auto_pilot?;
     let srb_resources = srb_resources?;
 
+    // Work out the launch heading needed to reach the target inclination, correcting for the
+    // body's rotation, instead of just pointing due east.
+    let body = client.mk_call(&orbit.get_body())?;
+    let (latitude, body_mu, body_equatorial_radius, body_rotation_period) = batch_call!(
+        &client,
+        (
+            &flight.get_latitude(),
+            &body.get_gravitational_parameter(),
+            &body.get_equatorial_radius(),
+            &body.get_rotational_period()
+        )
+    )?;
+
+    let latitude = latitude?;
+    let body_mu = body_mu? as f64;
+    let body_equatorial_radius = body_equatorial_radius? as f64;
+    let body_rotation_period = body_rotation_period? as f64;
+
+    // The pad latitude itself is the minimum inclination reachable without a plane change, so
+    // never ask for less than that.
+    let target_inclination = TARGET_INCLINATION.max(latitude.abs());
+    let (launch_heading, _) = util::launch_azimuth(
+        latitude,
+        target_inclination,
+        body_equatorial_radius + TARGET_ALTITUDE,
+        body_mu,
+        body_equatorial_radius,
+        body_rotation_period,
+    )?;
+    log::info!("Launch heading for [{}] deg inclination: [{}]", target_inclination, launch_heading);
+
     // Start Telemetry
-    let (alt_stream_handle, apoapsis_stream_handle, ut_stream_handle, srb_fuel_stream) = batch_call!(
+    let (
+        alt_stream_handle,
+        apoapsis_stream_handle,
+        ut_stream_handle,
+        srb_fuel_stream,
+        dynamic_pressure_stream_handle,
+        available_thrust_stream_handle,
+        mass_stream_handle,
+    ) = batch_call!(
         &client,
         (
             &flight.get_mean_altitude().to_stream(),
             &orbit.get_apoapsis_altitude().to_stream(),
             &space_center::get_ut().to_stream(),
-            &srb_resources.amount(SRB_FUEL.to_string()).to_stream()
+            &srb_resources.amount(SRB_FUEL.to_string()).to_stream(),
+            &flight.get_dynamic_pressure().to_stream(),
+            &vessel.get_available_thrust().to_stream(),
+            &vessel.get_mass().to_stream()
         )
     )?;
 
@@ -63,6 +113,26 @@ fn main() -> Result<()> {
     let apoapsis_stream_handle = apoapsis_stream_handle?;
     let ut_stream_handle = ut_stream_handle?;
     let srb_fuel_stream = srb_fuel_stream?;
+    let dynamic_pressure_stream_handle = dynamic_pressure_stream_handle?;
+    let available_thrust_stream_handle = available_thrust_stream_handle?;
+    let mass_stream_handle = mass_stream_handle?;
+
+    // Cap throttle against TWR and Max-Q limits during ascent, instead of always running flat out.
+    let throttle_governor = throttle::Governor::new(TARGET_TWR, MAX_DYNAMIC_PRESSURE);
+
+    // Fires once the SRBs run dry, instead of polling the raw resource amount for depletion.
+    let srb_depleted = events::resource_depleted(&client, &srb_resources, SRB_FUEL, 0.1)?;
+
+    // Log altitude/apoapsis/UT/SRB fuel to disk for offline replay.
+    let mut recorder = telemetry::Recorder::new(
+        TELEMETRY_LOG,
+        vec![
+            Box::new(telemetry::ScalarChannel::new("altitude", alt_stream_handle.clone())),
+            Box::new(telemetry::ScalarChannel::new("apoapsis", apoapsis_stream_handle.clone())),
+            Box::new(telemetry::ScalarChannel::new("ut", ut_stream_handle.clone())),
+            Box::new(telemetry::ScalarChannel::new("srb_fuel", srb_fuel_stream.clone())),
+        ],
+    )?;
 
     // Prepare to launch
     let _ = batch_call!(
@@ -80,28 +150,32 @@ fn main() -> Result<()> {
 
     log::debug!("Activating next stage");
     log::debug!("Engaging Auto Pilot");
-    log::debug!("Setting target pitch & headting [90, 90]");
+    log::debug!("Setting target pitch & heading [90, {}]", launch_heading);
     let _ = batch_call!(
         &client,
         (
-            &control.activate_next_stage(),                 // Next Stage
-            &auto_pilot.engage(),                           // Engage Auto-pilot
-            &auto_pilot.target_pitch_and_heading(90., 90.)  // Set Pitch and heading (90, 90)
+            &control.activate_next_stage(),  // Next Stage
+            &auto_pilot.engage(),             // Engage Auto-pilot
+            &auto_pilot.target_pitch_and_heading(90., launch_heading as f32)
         )
     )?;
 
     // 3. Main Ascent loop
-    let mut turn_angle = 0.0;
+    let ascent_profile = util::AscentProfile::new(TURN_START_ALT, TURN_END_ALT, launch_heading, 1.0);
+    let mut target_pitch = 90.0;
     let mut srb_seperated = false;
     let mut srb_fuel_seen_valid = false;
     loop {
-        let update = match get_telemetry_update(
+        let update = match get_ascent_telemetry_update(
             &stream_client,
             (
                 &ut_stream_handle,
                 &apoapsis_stream_handle,
                 &alt_stream_handle,
                 &srb_fuel_stream,
+                &dynamic_pressure_stream_handle,
+                &available_thrust_stream_handle,
+                &mass_stream_handle,
             ),
         ) {
             Ok(update) => update,
@@ -111,22 +185,50 @@ fn main() -> Result<()> {
             }
         };
 
-        if let (_, Ok(apoapsis), Ok(altitude), srb_fuel) = update {
+        let (raw_update, ut, apoapsis, altitude, srb_fuel, dynamic_pressure, available_thrust, mass) = update;
+
+        if let Ok(ut) = ut {
+            recorder.record(&raw_update, ut)?;
+        }
+
+        if let Ok(stats) = telemetry::ConnectionStats::poll(&client) {
+            log::trace!(
+                "kRPC bandwidth: [{:.2} KB/s read, {:.2} KB/s write]",
+                stats.bytes_read_rate_kbs,
+                stats.bytes_written_rate_kbs
+            );
+        }
+
+        if let (Ok(apoapsis), Ok(altitude), srb_fuel) = (apoapsis, altitude, srb_fuel) {
             // Gravity turn
             if altitude > TURN_START_ALT && altitude < TURN_END_ALT {
                 log::trace!("Gravity Turn Tick");
-                let frac = (altitude - TURN_START_ALT) / (TURN_END_ALT - TURN_START_ALT);
-
-                let new_turn_angle = frac * 90.;
-
-                if (new_turn_angle - turn_angle).abs() > 0.5 {
-                    turn_angle = new_turn_angle;
-                    client.mk_call(
-                        &auto_pilot.target_pitch_and_heading(90. - turn_angle as f32, 90.),
-                    )?;
+                let new_pitch = ascent_profile.pitch_at(altitude);
+
+                if (new_pitch - target_pitch).abs() > 0.5 {
+                    target_pitch = new_pitch;
+                    client.mk_call(&auto_pilot.target_pitch_and_heading(
+                        target_pitch as f32,
+                        ascent_profile.heading as f32,
+                    ))?;
                 }
             }
 
+            // Cap throttle to the TWR and Max-Q limits, instead of running flat out.
+            if let (Ok(dynamic_pressure), Ok(available_thrust), Ok(mass)) =
+                (dynamic_pressure, available_thrust, mass)
+            {
+                let current_twr = throttle::twr(
+                    available_thrust as f64,
+                    mass as f64,
+                    altitude,
+                    body_mu,
+                    body_equatorial_radius,
+                );
+                let throttle = throttle_governor.throttle_for(current_twr, dynamic_pressure as f64);
+                client.mk_call(&control.set_throttle(throttle))?;
+            }
+
             if let Ok(srb_fuel) = srb_fuel {
                 // SRB Booster Seperation
                 if !srb_seperated {
@@ -135,7 +237,7 @@ fn main() -> Result<()> {
                         srb_fuel_seen_valid = true;
                     }
 
-                    if srb_fuel <= 0.00 && srb_fuel_seen_valid {
+                    if srb_fuel_seen_valid && srb_depleted.is_set(&raw_update) {
                         log::info!("Detaching SRBs.");
                         client.mk_call(&control.activate_next_stage())?;
                         srb_seperated = true;
@@ -156,28 +258,7 @@ fn main() -> Result<()> {
     log::debug!("Lowering throttle to [25%]");
     client.mk_call(&control.set_throttle(0.25))?; // 25% Throttle
 
-    loop {
-        let update = match get_telemetry_update(
-            &stream_client,
-            (
-                &ut_stream_handle,
-                &apoapsis_stream_handle,
-                &alt_stream_handle,
-                &srb_fuel_stream
-            ),
-        ) {
-            Ok(update) => update,
-            Err(_why) => {
-                continue;
-            }
-        };
-
-        if let (_, Ok(apoapsis), _, _) = update {
-            if apoapsis >= TARGET_ALTITUDE {
-                break;
-            }
-        }
-    }
+    events::apoapsis_at_least(&client, &orbit, TARGET_ALTITUDE)?.wait(&stream_client)?;
 
     log::info!("Target apoapsis reached.");
     log::debug!("Lowering throttle to [0%]");
@@ -185,42 +266,16 @@ fn main() -> Result<()> {
 
     // 5. Coast out of atmosphere
     log::info!("Coasting out of atmosphere.");
-    loop {
-        let update = match get_telemetry_update(
-            &stream_client,
-            (
-                &ut_stream_handle,
-                &apoapsis_stream_handle,
-                &alt_stream_handle,
-                &srb_fuel_stream,
-            ),
-        ) {
-            Ok(update) => update,
-            Err(_why) => {
-                continue;
-            }
-        };
-
-        if let (_, _, Ok(altitude), _) = update {
-            if altitude >= 70500. {
-                break;
-            }
-        }
-    }
+    events::altitude_at_least(&client, &flight, 70500.)?.wait(&stream_client)?;
 
     // 6. Plan circularization burn (using vis-viva equation)
     log::info!("Planning circularization burn");
-    let body = client.mk_call(&orbit.get_body())?;
-    let (mu, a2, a1) = batch_call!(
+    let (a2, a1) = batch_call!(
         &client,
-        (
-            &body.get_gravitational_parameter(),
-            &orbit.get_apoapsis(),
-            &orbit.get_semi_major_axis()
-        )
+        (&orbit.get_apoapsis(), &orbit.get_semi_major_axis())
     )?;
 
-    let mu = mu? as f64;
+    let mu = body_mu;
     let a2 = a2?;
     let a1 = a1?;
     let v1 = (mu * ((2. / a2) - (1. / a1))).sqrt();
@@ -236,99 +291,8 @@ fn main() -> Result<()> {
     log::debug!("Creating maneuver node.");
     let node = client.mk_call(&control.add_node(ut? + time_to_apoapsis?, delta_v, 0., 0.))?;
 
-    // Calculate burn time (using rocket equation)
-    let (f, isp, m0) = batch_call!(
-        &client,
-        (
-            &vessel.get_available_thrust(),
-            &vessel.get_specific_impulse(),
-            &vessel.get_mass()
-        )
-    )?;
-
-    let f = f?;
-    let isp = isp? * 9.82;
-    let m0 = m0?;
-
-    let m1 = m0 / (delta_v / isp).exp();
-    let flow_rate = f / isp;
-    let burn_time = (m0 - m1) / flow_rate;
-
-    // Orientate ship
-    log::info!("Orientating ship for circularization burn");
-
-    log::info!("Getting reference frame.");
-    let node_reference_frame = client.mk_call(&node.get_reference_frame())?;
-    log::debug!("Reference Frame: {:?}", node_reference_frame);
-
-    log::debug!("Setting reference frame");
-    client.mk_call(&auto_pilot.set_reference_frame(&node_reference_frame))?;
-
-    log::debug!("Getting directional vector");
-    let (pitch, heading, roll) = client.mk_call(&flight.get_prograde())?;
-    log::debug!("Directional Vector: ({}, {}, {})", pitch, heading, roll);
-
-
-    log::debug!("Setting target direction");
-    // client.mk_call(&auto_pilot.set_target_direction((pitch, heading, roll)))?;
-    let _ = batch_call!(&client, (
-            &auto_pilot.set_target_pitch(pitch as f32),
-            &auto_pilot.set_target_heading(heading as f32)
-    ))?;
-
-    log::debug!("Waiting until oriented.");
-    client.mk_call(&auto_pilot.wait())?;
-    // let direction_offset_stream = client.mk_call(&auto_pilot.get_error().to_stream())?;
-    // loop {
-    //     let update = match stream_client.recv_update() {
-    //         Ok(update) => update,
-    //         Err(_) => continue
-    //     };
-
-    //     if let Ok(error) = update.get_result(&direction_offset_stream) {
-    //         if error <= 1. {
-    //             log::debug!("Oriented. Offset: [{:?}]", error);
-    //             break;
-    //         }
-    //     }
-    // }
-
-    // Wait until burn
-    log::info!("Waiting until circulization burn");
-    let (ut, time_to_apoapsis) = batch_call!(
-        &client,
-        (&space_center::get_ut(), &orbit.get_time_to_apoapsis())
-    )?;
-    let burn_ut = ut? + time_to_apoapsis? - (burn_time / 2.) as f64;
-    let lead_time = 5.;
-    log::debug!("Warping...");
-    client.mk_call(&space_center::warp_to(burn_ut - lead_time, 50., 4.))?;
-
-    // Execute burn
-    log::info!("Ready to execute burn");
-    let tta_stream_handle = client.mk_call(&orbit.get_time_to_apoapsis().to_stream())?;
-
-    loop {
-        let update = match stream_client.recv_update() {
-            Ok(update) => update,
-            Err(_) => continue,
-        };
-
-        if let Ok(tta) = update.get_result(&tta_stream_handle) {
-            if tta - (burn_time / 2.) as f64 <= 0. {
-                break;
-            }
-        }
-    }
-
-    log::info!("Executing burn");
-    client.mk_call(&control.set_throttle(1.0))?; // 100% Throttle
-
-    log::debug!("Sleeping for [{}] seconds.", burn_time);
-    std::thread::sleep(std::time::Duration::from_secs_f32(burn_time - 0.5));
-
-    // println!("Fine tuning");
-    // client.mk_call(&control.set_throttle(0.05))?; // 5% Throttle
+    // Orient, warp to and execute the burn.
+    maneuver::execute_node(&client, &stream_client, &vessel, &node)?;
 
     log::info!("Launch complete!");
 
@@ -338,28 +302,55 @@ fn main() -> Result<()> {
 type Result<T> = std::result::Result<T, Box<dyn Error>>;
 type StreamResult<T> = std::result::Result<T, krpc_mars::error::Error>;
 
-fn get_telemetry_update(
+fn get_ascent_telemetry_update(
     stream_client: &krpc_mars::StreamClient,
     handles: (
         &StreamHandle<f64>,
         &StreamHandle<f64>,
         &StreamHandle<f64>,
         &StreamHandle<f32>,
+        &StreamHandle<f32>,
+        &StreamHandle<f32>,
+        &StreamHandle<f32>,
     ),
 ) -> Result<(
+    StreamUpdate,
     StreamResult<f64>,
     StreamResult<f64>,
     StreamResult<f64>,
     StreamResult<f32>,
+    StreamResult<f32>,
+    StreamResult<f32>,
+    StreamResult<f32>,
 )> {
     let update = stream_client.recv_update()?;
 
-    let (ut_stream_handle, apoapsis_stream_handle, alt_stream_handle, srb_fuel_stream) = handles;
+    let (
+        ut_stream_handle,
+        apoapsis_stream_handle,
+        alt_stream_handle,
+        srb_fuel_stream,
+        dynamic_pressure_stream_handle,
+        available_thrust_stream_handle,
+        mass_stream_handle,
+    ) = handles;
 
     let ut_result = update.get_result(&ut_stream_handle);
     let apoapsis_result = update.get_result(&apoapsis_stream_handle);
     let altitude_result = update.get_result(&alt_stream_handle);
     let srb_fuel = update.get_result(&srb_fuel_stream);
-
-    Ok((ut_result, apoapsis_result, altitude_result, srb_fuel))
+    let dynamic_pressure = update.get_result(&dynamic_pressure_stream_handle);
+    let available_thrust = update.get_result(&available_thrust_stream_handle);
+    let mass = update.get_result(&mass_stream_handle);
+
+    Ok((
+        update,
+        ut_result,
+        apoapsis_result,
+        altitude_result,
+        srb_fuel,
+        dynamic_pressure,
+        available_thrust,
+        mass,
+    ))
 }
@@ -0,0 +1,70 @@
+use crate::krpc::{Event as RawEvent, Expression};
+use crate::space_center::{Flight, Orbit, Resources};
+
+use krpc_mars::{RPCClient, StreamClient, StreamHandle, StreamUpdate};
+use std::error::Error;
+
+type Result<T> = std::result::Result<T, Box<dyn Error>>;
+
+/// A server-evaluated condition, built from a kRPC `Expression`
+pub struct Event {
+    stream_handle: StreamHandle<bool>,
+}
+
+impl Event {
+    /// Registers `expression` with the server
+    pub fn from_expression(client: &RPCClient, expression: Expression) -> Result<Self> {
+        let inner = client.mk_call(&RawEvent::new(&expression))?;
+        let stream_handle = inner.get_stream();
+        Ok(Event { stream_handle })
+    }
+
+    /// Blocks until the expression evaluates to `true`
+    pub fn wait(&self, stream_client: &StreamClient) -> Result<()> {
+        loop {
+            let update = stream_client.recv_update()?;
+            if self.is_set(&update) {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks whether the expression is `true` in an already-received `update`, without blocking
+    pub fn is_set(&self, update: &StreamUpdate) -> bool {
+        matches!(update.get_result(&self.stream_handle), Ok(true))
+    }
+}
+
+/// Fires once `resource` in `resources` drops below `threshold`
+pub fn resource_depleted(
+    client: &RPCClient,
+    resources: &Resources,
+    resource: &str,
+    threshold: f32,
+) -> Result<Event> {
+    let amount = client.mk_call(&Expression::call(&resources.amount(resource.to_string())))?;
+    let threshold = client.mk_call(&Expression::constant_float(threshold))?;
+    let expr = client.mk_call(&Expression::less_than(&amount, &threshold))?;
+
+    Event::from_expression(client, expr)
+}
+
+/// Fires once the orbit's apoapsis altitude reaches at least `altitude`
+pub fn apoapsis_at_least(client: &RPCClient, orbit: &Orbit, altitude: f64) -> Result<Event> {
+    let current = client.mk_call(&Expression::call(&orbit.get_apoapsis_altitude()))?;
+    let target = client.mk_call(&Expression::constant_double(altitude))?;
+    let expr = client.mk_call(&Expression::greater_than_or_equal(&current, &target))?;
+
+    Event::from_expression(client, expr)
+}
+
+/// Fires once the vessel's altitude reaches at least `altitude`
+pub fn altitude_at_least(client: &RPCClient, flight: &Flight, altitude: f64) -> Result<Event> {
+    let current = client.mk_call(&Expression::call(&flight.get_mean_altitude()))?;
+    let target = client.mk_call(&Expression::constant_double(altitude))?;
+    let expr = client.mk_call(&Expression::greater_than_or_equal(&current, &target))?;
+
+    Event::from_expression(client, expr)
+}
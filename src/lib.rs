@@ -2,10 +2,16 @@
 extern crate krpc_mars;
 
 pub mod drawing;
+pub mod events;
 pub mod infernal_robotics;
 pub mod kerbal_alarm_clock;
+pub mod krpc;
+pub mod maneuver;
+pub mod navigation;
 pub mod remote_tech;
 pub mod space_center;
+pub mod telemetry;
+pub mod throttle;
 pub mod ui;
 
 pub mod util {
@@ -19,4 +25,140 @@ pub mod util {
         log::info!("Ignition!")
     }
 
+    /// Error returned when `target_inclination` is unreachable from `latitude`
+    #[derive(Debug)]
+    pub struct UnreachableInclination {
+        pub latitude: f64,
+        pub target_inclination: f64,
+    }
+
+    impl std::fmt::Display for UnreachableInclination {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(
+                f,
+                "target inclination {} is unreachable from latitude {} (minimum inclination is |latitude|)",
+                self.target_inclination, self.latitude
+            )
+        }
+    }
+
+    impl std::error::Error for UnreachableInclination {}
+
+    /// Rotation-corrected `(ascending_heading, descending_heading)` needed to reach
+    /// `target_inclination` from `latitude`, in degrees
+    pub fn launch_azimuth(
+        latitude: f64,
+        target_inclination: f64,
+        target_orbit_radius: f64,
+        body_mu: f64,
+        body_equatorial_radius: f64,
+        body_rotation_period: f64,
+    ) -> Result<(f64, f64), UnreachableInclination> {
+        let lat_rad = latitude.to_radians();
+        let inc_rad = target_inclination.to_radians();
+
+        let cos_arg = inc_rad.cos() / lat_rad.cos();
+        if !(cos_arg.abs() <= 1.0) {
+            return Err(UnreachableInclination {
+                latitude,
+                target_inclination,
+            });
+        }
+
+        // Target orbital speed (vis-viva for a circular orbit) and the body's surface speed
+        // at the launch latitude, used to correct inertial heading for the body's rotation.
+        let v0 = (body_mu / target_orbit_radius).sqrt();
+        let vg = 2.0 * std::f64::consts::PI * body_equatorial_radius / body_rotation_period
+            * lat_rad.cos();
+
+        let azimuth_ascending = cos_arg.asin();
+        let azimuth_descending = std::f64::consts::PI - azimuth_ascending;
+
+        let heading_for = |azimuth: f64| -> f64 {
+            let vx = v0 * azimuth.sin() - vg;
+            let vy = v0 * azimuth.cos();
+            (vx.atan2(vy).to_degrees() + 360.0) % 360.0
+        };
+
+        Ok((heading_for(azimuth_ascending), heading_for(azimuth_descending)))
+    }
+
+    /// Target pitch for a shaped gravity turn between `start_alt` and `target_apoapsis`
+    pub fn gravity_turn_pitch(
+        altitude: f64,
+        start_alt: f64,
+        target_apoapsis: f64,
+        exponent: f64,
+    ) -> f64 {
+        let fraction = ((altitude - start_alt) / (target_apoapsis - start_alt)).clamp(0.0, 1.0);
+        90.0 * (1.0 - fraction.powf(exponent))
+    }
+
+    /// The pitch/heading parameters of a gravity-turn ascent
+    pub struct AscentProfile {
+        pub start_alt: f64,
+        pub target_apoapsis: f64,
+        pub heading: f64,
+        pub exponent: f64,
+    }
+
+    impl AscentProfile {
+        pub fn new(start_alt: f64, target_apoapsis: f64, heading: f64, exponent: f64) -> Self {
+            AscentProfile {
+                start_alt,
+                target_apoapsis,
+                heading,
+                exponent,
+            }
+        }
+
+        /// Target pitch at the given altitude
+        pub fn pitch_at(&self, altitude: f64) -> f64 {
+            gravity_turn_pitch(altitude, self.start_alt, self.target_apoapsis, self.exponent)
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn launch_azimuth_due_east_from_equator_to_equatorial_orbit() {
+            // Kerbin: mu, equatorial radius and sidereal rotation period.
+            let (ascending, descending) = launch_azimuth(
+                0.,
+                0.,
+                600_000. + 100_000.,
+                3.5316e12,
+                600_000.,
+                21_549.425,
+            )
+            .unwrap();
+
+            assert!((ascending - 90.).abs() < 1e-6);
+            assert!((descending - 90.).abs() < 1e-6);
+        }
+
+        #[test]
+        fn launch_azimuth_rejects_unreachable_inclination() {
+            assert!(launch_azimuth(45., 0., 700_000., 3.5316e12, 600_000., 21_549.425).is_err());
+        }
+
+        #[test]
+        fn launch_azimuth_at_the_pole_matches_minimum_inclination_without_nan() {
+            // latitude == target_inclination is the one reachable case at the pole, and the one
+            // most likely to hit the 0.0/0.0 NaN this guards against.
+            let (ascending, descending) =
+                launch_azimuth(90., 90., 700_000., 3.5316e12, 600_000., 21_549.425).unwrap();
+
+            assert!(!ascending.is_nan());
+            assert!(!descending.is_nan());
+        }
+
+        #[test]
+        fn gravity_turn_pitch_clamps_to_endpoints() {
+            assert_eq!(gravity_turn_pitch(0., 250., 45_000., 1.0), 90.);
+            assert_eq!(gravity_turn_pitch(45_000., 250., 45_000., 1.0), 0.);
+        }
+    }
 }
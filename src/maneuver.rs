@@ -0,0 +1,125 @@
+use crate::space_center::{Node, Vessel};
+
+use krpc_mars::{batch_call, RPCClient, StreamClient};
+use std::error::Error;
+
+type Result<T> = std::result::Result<T, Box<dyn Error>>;
+
+/// Gravitational acceleration `g0`, in m/s², used to convert specific impulse (seconds) into effective exhaust velocity.
+const G0: f64 = 9.82;
+
+/// Pointing error, in degrees, below which the ship is considered "oriented"
+const POINTING_ERROR_THRESHOLD: f32 = 0.5;
+
+/// Lead time, in seconds, to stop time-warping before the calculated burn start
+const WARP_LEAD_TIME: f64 = 5.;
+
+/// Remaining delta-v, in m/s, below which the burn switches to a fine-tune throttle
+const FINE_TUNE_DELTA_V: f32 = 5.;
+
+/// Throttle used once the burn is within `FINE_TUNE_DELTA_V` of completion.
+const FINE_TUNE_THROTTLE: f32 = 0.05;
+
+/// Orients, warps to and flies a maneuver `node` on `vessel`.
+pub fn execute_node(
+    client: &RPCClient,
+    stream_client: &StreamClient,
+    vessel: &Vessel,
+    node: &Node,
+) -> Result<()> {
+    let control = client.mk_call(&vessel.get_control())?;
+    let auto_pilot = client.mk_call(&vessel.get_auto_pilot())?;
+
+    // Burn time, from the Tsiolkovsky rocket equation.
+    let (available_thrust, specific_impulse, mass, delta_v) = batch_call!(
+        client,
+        (
+            &vessel.get_available_thrust(),
+            &vessel.get_specific_impulse(),
+            &vessel.get_mass(),
+            &node.get_delta_v()
+        )
+    )?;
+
+    let f = available_thrust? as f64;
+    let isp = specific_impulse? as f64 * G0;
+    let m0 = mass? as f64;
+    let delta_v = delta_v?;
+
+    let m1 = m0 / (delta_v / isp).exp();
+    let flow_rate = f / isp;
+    let burn_time = (m0 - m1) / flow_rate;
+
+    // Orient the ship to the node's burn vector.
+    log::info!("Orienting ship for burn.");
+    let node_reference_frame = client.mk_call(&node.get_reference_frame())?;
+    let burn_vector = client.mk_call(&node.get_burn_vector(&node_reference_frame))?;
+
+    client.mk_call(&auto_pilot.set_reference_frame(&node_reference_frame))?;
+    client.mk_call(&auto_pilot.set_target_direction(burn_vector))?;
+    client.mk_call(&auto_pilot.engage())?;
+
+    let error_stream = client.mk_call(&auto_pilot.get_error().to_stream())?;
+    loop {
+        let update = stream_client.recv_update()?;
+        if let Ok(error) = update.get_result(&error_stream) {
+            if error <= POINTING_ERROR_THRESHOLD {
+                log::debug!("Oriented. Offset: [{:?}]", error);
+                break;
+            }
+        }
+    }
+
+    // Warp to the burn, stopping short so the warp has settled before ignition.
+    let (ut, time_to_node) = batch_call!(client, (&crate::space_center::get_ut(), &node.get_time_to()))?;
+    let burn_ut = ut? + time_to_node? - (burn_time / 2.);
+    log::debug!("Warping to burn.");
+    client.mk_call(&crate::space_center::warp_to(burn_ut - WARP_LEAD_TIME, 50., 4.))?;
+
+    let time_to_node_stream = client.mk_call(&node.get_time_to().to_stream())?;
+    loop {
+        let update = stream_client.recv_update()?;
+        if let Ok(time_to_node) = update.get_result(&time_to_node_stream) {
+            if time_to_node - (burn_time / 2.) as f64 <= 0. {
+                break;
+            }
+        }
+    }
+
+    // Execute the burn off the live remaining delta-v, rather than a fixed sleep.
+    log::info!("Executing burn.");
+    let remaining_delta_v_stream = client.mk_call(&node.get_remaining_delta_v().to_stream())?;
+    client.mk_call(&control.set_throttle(1.0))?;
+
+    let mut last_remaining = delta_v as f32;
+    loop {
+        let update = stream_client.recv_update()?;
+        let remaining = match update.get_result(&remaining_delta_v_stream) {
+            Ok(remaining) => remaining,
+            Err(_) => continue,
+        };
+
+        if remaining <= 0.05 {
+            log::info!("Burn complete.");
+            break;
+        }
+
+        // Overshoot guard: the remaining vector has flipped direction (it started growing
+        // again), so cut throttle immediately instead of burning past the node.
+        if remaining > last_remaining {
+            log::warn!("Overshoot detected, cutting throttle.");
+            break;
+        }
+
+        if remaining <= FINE_TUNE_DELTA_V {
+            client.mk_call(&control.set_throttle(FINE_TUNE_THROTTLE))?;
+        }
+
+        last_remaining = remaining;
+    }
+
+    client.mk_call(&control.set_throttle(0.0))?;
+    client.mk_call(&node.remove())?;
+
+    Ok(())
+}
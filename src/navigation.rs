@@ -0,0 +1,62 @@
+/// Haversine great-circle distance between two lat/lng points (in degrees) on a sphere of `radius`
+pub fn great_circle_distance(lat1: f64, lng1: f64, lat2: f64, lng2: f64, radius: f64) -> f64 {
+    let lat1 = lat1.to_radians();
+    let lat2 = lat2.to_radians();
+    let dlat = (lat1 - lat2) / 2.0;
+    let dlng = (lng1 - lng2).to_radians() / 2.0;
+
+    let a = dlat.sin().powi(2) + lat1.cos() * lat2.cos() * dlng.sin().powi(2);
+
+    2.0 * radius * a.sqrt().atan2((1.0 - a).sqrt())
+}
+
+/// Compass heading, in degrees `[0, 360)`, of `facing` given the surface `north`/`up` vectors
+pub fn compass_heading(facing: (f64, f64, f64), north: (f64, f64, f64), up: (f64, f64, f64)) -> f64 {
+    let east = cross(up, north);
+
+    let heading = dot(east, facing).atan2(dot(north, facing)).to_degrees();
+    (heading + 360.0) % 360.0
+}
+
+fn cross(a: (f64, f64, f64), b: (f64, f64, f64)) -> (f64, f64, f64) {
+    (
+        a.1 * b.2 - a.2 * b.1,
+        a.2 * b.0 - a.0 * b.2,
+        a.0 * b.1 - a.1 * b.0,
+    )
+}
+
+fn dot(a: (f64, f64, f64), b: (f64, f64, f64)) -> f64 {
+    a.0 * b.0 + a.1 * b.1 + a.2 * b.2
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn great_circle_distance_quarter_way_around() {
+        let distance = great_circle_distance(0., 0., 0., 90., 1.0);
+        assert!((distance - std::f64::consts::FRAC_PI_2).abs() < 1e-9);
+    }
+
+    #[test]
+    fn great_circle_distance_same_point_is_zero() {
+        assert_eq!(great_circle_distance(12.3, 45.6, 12.3, 45.6, 600_000.), 0.);
+    }
+
+    #[test]
+    fn compass_heading_facing_north() {
+        let up = (0., 0., 1.);
+        let north = (0., 1., 0.);
+        assert_eq!(compass_heading(north, north, up), 0.);
+    }
+
+    #[test]
+    fn compass_heading_facing_east() {
+        let up = (0., 0., 1.);
+        let north = (0., 1., 0.);
+        let east = cross(up, north);
+        assert_eq!(compass_heading(east, north, up), 90.);
+    }
+}
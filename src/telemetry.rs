@@ -0,0 +1,124 @@
+use crate::krpc;
+
+use krpc_mars::{RPCClient, StreamHandle, StreamUpdate};
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+type Result<T> = std::result::Result<T, Box<dyn Error>>;
+
+/// One named value pulled out of a stream update
+pub trait Channel {
+    fn name(&self) -> &str;
+    fn value(&self, update: &StreamUpdate) -> Option<String>;
+}
+
+/// A scalar channel (`f64` or `f32`)
+pub struct ScalarChannel<T> {
+    name: String,
+    handle: StreamHandle<T>,
+}
+
+impl<T> ScalarChannel<T> {
+    pub fn new(name: impl Into<String>, handle: StreamHandle<T>) -> Self {
+        ScalarChannel {
+            name: name.into(),
+            handle,
+        }
+    }
+}
+
+impl Channel for ScalarChannel<f64> {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn value(&self, update: &StreamUpdate) -> Option<String> {
+        let v = update.get_result(&self.handle).ok()?;
+        v.is_finite().then(|| v.to_string())
+    }
+}
+
+impl Channel for ScalarChannel<f32> {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn value(&self, update: &StreamUpdate) -> Option<String> {
+        let v = update.get_result(&self.handle).ok()?;
+        v.is_finite().then(|| v.to_string())
+    }
+}
+
+/// A `(f64, f64, f64)` vector channel
+pub struct VectorChannel {
+    name: String,
+    handle: StreamHandle<(f64, f64, f64)>,
+}
+
+impl VectorChannel {
+    pub fn new(name: impl Into<String>, handle: StreamHandle<(f64, f64, f64)>) -> Self {
+        VectorChannel {
+            name: name.into(),
+            handle,
+        }
+    }
+}
+
+impl Channel for VectorChannel {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn value(&self, update: &StreamUpdate) -> Option<String> {
+        let (x, y, z) = update.get_result(&self.handle).ok()?;
+        (x.is_finite() && y.is_finite() && z.is_finite()).then(|| format!("[{},{},{}]", x, y, z))
+    }
+}
+
+/// Logs a set of channels to a JSON-lines sink
+pub struct Recorder {
+    channels: Vec<Box<dyn Channel>>,
+    sink: BufWriter<File>,
+}
+
+impl Recorder {
+    pub fn new(path: &str, channels: Vec<Box<dyn Channel>>) -> Result<Self> {
+        Ok(Recorder {
+            channels,
+            sink: BufWriter::new(File::create(path)?),
+        })
+    }
+
+    /// Appends one row, keyed by the in-game `ut`
+    pub fn record(&mut self, update: &StreamUpdate, ut: f64) -> Result<()> {
+        let mut row = format!("{{\"ut\":{}", ut);
+        for channel in &self.channels {
+            if let Some(value) = channel.value(update) {
+                row.push_str(&format!(",\"{}\":{}", channel.name(), value));
+            }
+        }
+        row.push('}');
+
+        writeln!(self.sink, "{}", row)?;
+        Ok(())
+    }
+}
+
+/// Connection bandwidth, in KB/s
+pub struct ConnectionStats {
+    pub bytes_read_rate_kbs: f64,
+    pub bytes_written_rate_kbs: f64,
+}
+
+impl ConnectionStats {
+    /// Polls the server status for the current read/write rates
+    pub fn poll(client: &RPCClient) -> Result<Self> {
+        let status = client.mk_call(&krpc::get_status())?;
+
+        Ok(ConnectionStats {
+            bytes_read_rate_kbs: status.bytes_read_rate / 1024.0,
+            bytes_written_rate_kbs: status.bytes_written_rate / 1024.0,
+        })
+    }
+}
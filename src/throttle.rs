@@ -0,0 +1,82 @@
+/// Throttle fraction that caps TWR at `target_twr`
+pub fn twr_limited(current_twr: f64, target_twr: f64) -> f32 {
+    if current_twr <= target_twr {
+        1.0
+    } else {
+        (target_twr / current_twr) as f32
+    }
+}
+
+/// Throttle fraction that caps dynamic pressure at `q_threshold`
+pub fn max_q_limited(dynamic_pressure: f64, q_threshold: f64) -> f32 {
+    if dynamic_pressure <= q_threshold {
+        1.0
+    } else {
+        (q_threshold / dynamic_pressure) as f32
+    }
+}
+
+/// Thrust-to-weight ratio at the given altitude above a body
+pub fn twr(available_thrust: f64, mass: f64, altitude: f64, body_mu: f64, body_radius: f64) -> f64 {
+    let gravity = body_mu / (altitude + body_radius).powi(2);
+    available_thrust / (mass * gravity)
+}
+
+/// TWR and Max-Q throttle limiter, fed per tick
+pub struct Governor {
+    pub target_twr: f64,
+    pub q_threshold: f64,
+}
+
+impl Governor {
+    pub fn new(target_twr: f64, q_threshold: f64) -> Self {
+        Governor {
+            target_twr,
+            q_threshold,
+        }
+    }
+
+    /// Throttle fraction respecting both limits
+    pub fn throttle_for(&self, current_twr: f64, dynamic_pressure: f64) -> f32 {
+        twr_limited(current_twr, self.target_twr).min(max_q_limited(dynamic_pressure, self.q_threshold))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn twr_limited_full_throttle_below_target() {
+        assert_eq!(twr_limited(1.5, 2.0), 1.0);
+    }
+
+    #[test]
+    fn twr_limited_throttles_down_above_target() {
+        assert_eq!(twr_limited(4.0, 2.0), 0.5);
+    }
+
+    #[test]
+    fn max_q_limited_full_throttle_below_threshold() {
+        assert_eq!(max_q_limited(10_000., 24_000.), 1.0);
+    }
+
+    #[test]
+    fn max_q_limited_throttles_down_above_threshold() {
+        assert_eq!(max_q_limited(48_000., 24_000.), 0.5);
+    }
+
+    #[test]
+    fn twr_at_kerbin_sea_level() {
+        // 200kN thrust, 10t craft, standing on Kerbin's surface.
+        let ratio = twr(200_000., 10_000., 0., 3.5316e12, 600_000.);
+        assert!((ratio - 2.0387).abs() < 1e-3);
+    }
+
+    #[test]
+    fn governor_takes_the_tighter_limit() {
+        let governor = Governor::new(2.0, 24_000.);
+        assert_eq!(governor.throttle_for(4.0, 10_000.), 0.5);
+        assert_eq!(governor.throttle_for(1.0, 48_000.), 0.5);
+    }
+}